@@ -0,0 +1,477 @@
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::rc::Rc;
+use std::sync::mpsc::{Receiver, TryRecvError};
+
+use sdl2::event::Event;
+use sdl2::keyboard::{Keycode, Mod};
+use sdl2::pixels::Color;
+use sdl2::rect::Rect;
+use sdl2::render::{Canvas, Texture, TextureCreator, TextureQuery};
+use sdl2::ttf::Font;
+use sdl2::video::{Window, WindowContext};
+use sdl2::VideoSubsystem;
+
+use rustybuzz::Face;
+
+use crate::compositor::{Component, EventResult};
+use crate::line::Style;
+use crate::process::{self, Stream};
+use crate::scrollback::Scrollback;
+use crate::shaping;
+use crate::{is_copy_event, is_cut_event, is_paste_event, AppAction};
+
+/// How many lines a PageUp/PageDown keypress scrolls the viewport by.
+const PAGE_SCROLL_LINES: usize = 20;
+/// How many lines a single mouse wheel notch scrolls the viewport by.
+const WHEEL_SCROLL_LINES: usize = 3;
+
+/// Draws `texture` at `(x, y)` and returns its height, so callers can stack lines.
+fn blit(canvas: &mut Canvas<Window>, texture: &Texture, x: i32, y: i32) -> Result<i32, String> {
+    let TextureQuery { width, height, .. } = texture.query();
+    let target = Rect::new(x, y, width, height);
+    canvas.copy(texture, None, Some(target))?;
+    Ok(height as i32)
+}
+
+/// Identifies a cached line texture: its text plus whatever style it was drawn with,
+/// so a style change invalidates the cache entry too.
+#[derive(Clone, PartialEq, Eq, Hash)]
+struct CacheKey {
+    text: String,
+    style: Style,
+}
+
+/// Scrollback of past command output. Read-only from the UI's side; [`Prompt`] appends
+/// to it line by line as a running command's output arrives.
+///
+/// Rendering the whole buffer into one texture every frame costs O(total output), which
+/// gets worse the longer a session runs. Instead each line is rasterized once and kept
+/// in a texture cache modeled on Zed's `TextLayoutCache`: `curr_frame` is filled as lines
+/// are drawn this frame, falling back to `prev_frame` on a cache hit so nothing is
+/// rerendered while it's still on screen; at the end of the frame the maps swap and
+/// `curr_frame` is cleared, so lines that scrolled out of view simply age out.
+///
+/// Only draws the slice of lines that fit `area`'s height, starting `scroll_offset`
+/// lines up from the bottom, giving the usual terminal scrollback behavior: `0` tracks
+/// the live tail, and PageUp/PageDown/the mouse wheel move the window back through
+/// history without touching the underlying buffer.
+pub struct BufferView {
+    buffer: Rc<RefCell<Scrollback>>,
+    texture_creator: &'static TextureCreator<WindowContext>,
+    prev_frame: RefCell<HashMap<CacheKey, Texture<'static>>>,
+    curr_frame: RefCell<HashMap<CacheKey, Texture<'static>>>,
+    shaping_face: Option<Rc<Face<'static>>>,
+    scroll_offset: usize,
+}
+
+impl BufferView {
+    pub fn new(
+        buffer: Rc<RefCell<Scrollback>>,
+        texture_creator: &'static TextureCreator<WindowContext>,
+        shaping_face: Option<Rc<Face<'static>>>,
+    ) -> Self {
+        Self {
+            buffer,
+            texture_creator,
+            prev_frame: RefCell::new(HashMap::new()),
+            curr_frame: RefCell::new(HashMap::new()),
+            shaping_face,
+            scroll_offset: 0,
+        }
+    }
+
+    /// Returns the slice of `lines` that fits `area`'s height, ending `scroll_offset`
+    /// lines up from the end of the buffer.
+    fn visible_lines<'a>(&self, lines: &'a [crate::line::Line], area: Rect, font: &Font) -> &'a [crate::line::Line] {
+        let line_height = font.height().max(1);
+        let visible_count = ((area.height() as i32 / line_height).max(1)) as usize;
+        let total = lines.len();
+        let max_offset = total.saturating_sub(visible_count);
+        let offset = self.scroll_offset.min(max_offset);
+        let end = total - offset;
+        let start = end.saturating_sub(visible_count);
+        &lines[start..end]
+    }
+}
+
+impl Component for BufferView {
+    fn render(&self, area: Rect, canvas: &mut Canvas<Window>, font: &Font) -> Result<(), String> {
+        let scrollback = self.buffer.borrow();
+        let visible = self.visible_lines(scrollback.lines(), area, font);
+        let mut y_offset = area.y();
+
+        // The shaped path doesn't go through the line cache: it rasterizes per cluster
+        // already, so there's little left to cache, and it's opt-in/rare enough not to
+        // be worth a second cache keyed by shaped runs.
+        if let Some(face) = &self.shaping_face {
+            for line in visible {
+                let (r, g, b, a) = line.style.color();
+                y_offset += shaping::shape_and_blit(
+                    canvas,
+                    font,
+                    face,
+                    &line.text,
+                    Color::RGBA(r, g, b, a),
+                    area.x(),
+                    y_offset,
+                )?;
+            }
+            return Ok(());
+        }
+
+        for line in visible {
+            let key = CacheKey {
+                text: line.text.clone(),
+                style: line.style,
+            };
+
+            // A line repeated within the same frame (e.g. two blank lines) is already
+            // in `curr_frame`; otherwise fall back to what was drawn last frame, and
+            // only rasterize on a genuine miss.
+            if let Some(texture) = self.curr_frame.borrow().get(&key) {
+                y_offset += blit(canvas, texture, area.x(), y_offset)?;
+                continue;
+            }
+
+            let texture = match self.prev_frame.borrow_mut().remove(&key) {
+                Some(texture) => texture,
+                None => {
+                    let (r, g, b, a) = line.style.color();
+                    let surface = font
+                        .render(if line.text.is_empty() { " " } else { &line.text })
+                        .blended(Color::RGBA(r, g, b, a))
+                        .map_err(|e| e.to_string())?;
+                    self.texture_creator
+                        .create_texture_from_surface(&surface)
+                        .map_err(|e| e.to_string())?
+                }
+            };
+
+            y_offset += blit(canvas, &texture, area.x(), y_offset)?;
+            self.curr_frame.borrow_mut().insert(key, texture);
+        }
+
+        self.prev_frame.swap(&self.curr_frame);
+        self.curr_frame.borrow_mut().clear();
+
+        Ok(())
+    }
+
+    fn handle_event(&mut self, event: &Event) -> EventResult {
+        match event {
+            Event::KeyDown {
+                keycode: Some(Keycode::PageUp),
+                ..
+            } => {
+                self.scroll_offset = self.scroll_offset.saturating_add(PAGE_SCROLL_LINES);
+                EventResult::Consumed(None)
+            }
+            Event::KeyDown {
+                keycode: Some(Keycode::PageDown),
+                ..
+            } => {
+                self.scroll_offset = self.scroll_offset.saturating_sub(PAGE_SCROLL_LINES);
+                EventResult::Consumed(None)
+            }
+            Event::MouseWheel { y, .. } if *y != 0 => {
+                let delta = y.unsigned_abs() as usize * WHEEL_SCROLL_LINES;
+                if *y > 0 {
+                    self.scroll_offset = self.scroll_offset.saturating_add(delta);
+                } else {
+                    self.scroll_offset = self.scroll_offset.saturating_sub(delta);
+                }
+                EventResult::Consumed(None)
+            }
+            _ => EventResult::Ignored,
+        }
+    }
+
+    /// Docks to the top, leaving one line at the bottom (`font.height()` tall) free for
+    /// [`Prompt`] instead of drawing underneath it.
+    fn layout(&self, area: Rect, font: &Font) -> Rect {
+        let height = (area.height() as i32 - font.height()).max(0) as u32;
+        Rect::new(area.x(), area.y(), area.width(), height)
+    }
+}
+
+/// The command line: an editable prompt that owns `command_line`, talks to the
+/// clipboard, and appends a running command's output into the shared scrollback buffer
+/// as it arrives.
+pub struct Prompt {
+    prefix: &'static str,
+    command_line: Rc<RefCell<String>>,
+    buffer: Rc<RefCell<Scrollback>>,
+    video_subsys: VideoSubsystem,
+    shaping_face: Option<Rc<Face<'static>>>,
+    running: RefCell<Option<Receiver<process::OutputLine>>>,
+}
+
+impl Prompt {
+    pub fn new(
+        buffer: Rc<RefCell<Scrollback>>,
+        video_subsys: VideoSubsystem,
+        shaping_face: Option<Rc<Face<'static>>>,
+    ) -> Self {
+        Self {
+            prefix: "> ",
+            command_line: Rc::new(RefCell::new(String::new())),
+            buffer,
+            video_subsys,
+            shaping_face,
+            running: RefCell::new(None),
+        }
+    }
+
+    fn append_output(&self, stream: Stream, text: &str) {
+        let style = if stream == Stream::Stderr { Style::Stderr } else { Style::Normal };
+        self.buffer.borrow_mut().push(text, style);
+    }
+
+    /// Drains whatever the running command's background threads have sent since the
+    /// last frame, appending it to the scrollback incrementally instead of blocking
+    /// until the command finishes.
+    fn drain_running_command(&self) {
+        let mut running = self.running.borrow_mut();
+        let Some(receiver) = running.as_ref() else {
+            return;
+        };
+
+        loop {
+            match receiver.try_recv() {
+                Ok(output) => self.append_output(output.stream, &output.text),
+                Err(TryRecvError::Empty) => return,
+                Err(TryRecvError::Disconnected) => {
+                    *running = None;
+                    return;
+                }
+            }
+        }
+    }
+}
+
+impl Component for Prompt {
+    fn render(&self, area: Rect, canvas: &mut Canvas<Window>, font: &Font) -> Result<(), String> {
+        self.drain_running_command();
+
+        let color = Color::RGBA(170, 170, 170, 255);
+        let line = format!("{}{}", self.prefix, self.command_line.borrow());
+
+        if let Some(face) = &self.shaping_face {
+            shaping::shape_and_blit(canvas, font, face, &line, color, area.x(), area.y())?;
+            return Ok(());
+        }
+
+        let surface = font
+            .render(&line)
+            .blended_wrapped(color, 0)
+            .map_err(|e| e.to_string())?;
+        let texture_creator = canvas.texture_creator();
+        let texture = texture_creator
+            .create_texture_from_surface(&surface)
+            .map_err(|e| e.to_string())?;
+
+        let target = Rect::new(area.x(), area.y(), surface.width(), surface.height());
+        canvas.copy(&texture, None, Some(target))
+    }
+
+    /// Docks to the bottom of the screen in a single line, `font.height()` tall, so it
+    /// sits below [`BufferView`]'s output instead of overlapping it.
+    fn layout(&self, area: Rect, font: &Font) -> Rect {
+        let height = font.height().max(0) as u32;
+        let y = area.y() + area.height() as i32 - height as i32;
+        Rect::new(area.x(), y, area.width(), height)
+    }
+
+    fn handle_event(&mut self, event: &Event) -> EventResult {
+        match event {
+            Event::TextInput { text, .. } => {
+                self.command_line.borrow_mut().push_str(text);
+                EventResult::Consumed(None)
+            }
+            Event::TextEditing { .. } => EventResult::Consumed(None),
+            Event::KeyDown {
+                keycode: Some(Keycode::Escape),
+                ..
+            }
+            | Event::Quit { .. } => EventResult::Consumed(Some(AppAction::Exit)),
+            Event::KeyDown {
+                keycode: Some(Keycode::Return),
+                ..
+            } => {
+                let command_line = self.command_line.borrow().clone();
+                if !command_line.trim().is_empty() {
+                    match process::spawn(&command_line) {
+                        Ok(receiver) => *self.running.borrow_mut() = Some(receiver),
+                        Err(error) => self.append_output(Stream::Stderr, &error),
+                    }
+                }
+                self.command_line.borrow_mut().clear();
+                EventResult::Consumed(None)
+            }
+            Event::KeyDown {
+                keycode: Some(Keycode::Tab),
+                ..
+            } => {
+                let prefix = self
+                    .command_line
+                    .borrow()
+                    .split_ascii_whitespace()
+                    .next()
+                    .unwrap_or("")
+                    .to_string();
+                if prefix.is_empty() {
+                    return EventResult::Consumed(None);
+                }
+
+                let candidates = crate::find_command_candidates(&prefix);
+                if candidates.is_empty() {
+                    return EventResult::Consumed(None);
+                }
+
+                EventResult::Consumed(Some(AppAction::ShowCompletions {
+                    command_line: Rc::clone(&self.command_line),
+                    prefix_len: prefix.len(),
+                    candidates,
+                }))
+            }
+            Event::KeyDown {
+                keycode: Some(Keycode::Backspace),
+                keymod: Mod::LSHIFTMOD,
+                ..
+            } => {
+                let mut command_line = self.command_line.borrow_mut();
+                let mut words: Vec<&str> = command_line.split_ascii_whitespace().collect();
+                words.pop();
+                *command_line = words.join(" ");
+                EventResult::Consumed(None)
+            }
+            Event::KeyDown {
+                keycode: Some(Keycode::Backspace),
+                ..
+            } => {
+                self.command_line.borrow_mut().pop();
+                EventResult::Consumed(None)
+            }
+            event if is_copy_event(event) => {
+                let clipboard = self.video_subsys.clipboard();
+                if let Err(error) = clipboard.set_clipboard_text(&self.command_line.borrow()) {
+                    self.append_output(Stream::Stderr, &error);
+                }
+                EventResult::Consumed(None)
+            }
+            event if is_cut_event(event) => {
+                let clipboard = self.video_subsys.clipboard();
+                if let Err(error) = clipboard.set_clipboard_text(&self.command_line.borrow()) {
+                    self.append_output(Stream::Stderr, &error);
+                }
+                self.command_line.borrow_mut().clear();
+                EventResult::Consumed(None)
+            }
+            event if is_paste_event(event) => {
+                let clipboard = self.video_subsys.clipboard();
+                if clipboard.has_clipboard_text() {
+                    if let Ok(text) = clipboard.clipboard_text() {
+                        self.command_line.borrow_mut().push_str(&text);
+                    }
+                }
+                EventResult::Consumed(None)
+            }
+            _ => EventResult::Ignored,
+        }
+    }
+}
+
+/// A Tab-triggered popup listing `PATH` entries that match the command being typed.
+/// Pushed on top of the compositor stack when [`Prompt`] returns
+/// `AppAction::ShowCompletions`, so it gets first refusal on
+/// input — Tab cycles the highlighted candidate, Enter commits it back into the
+/// prompt's command line, matching Helix's prompt completion menu.
+pub struct CompletionPopup {
+    command_line: Rc<RefCell<String>>,
+    prefix_len: usize,
+    candidates: Vec<String>,
+    selected: usize,
+}
+
+impl CompletionPopup {
+    pub fn new(command_line: Rc<RefCell<String>>, prefix_len: usize, candidates: Vec<String>) -> Self {
+        Self {
+            command_line,
+            prefix_len,
+            candidates,
+            selected: 0,
+        }
+    }
+
+    fn commit(&self) {
+        let Some(candidate) = self.candidates.get(self.selected) else {
+            return;
+        };
+        let mut command_line = self.command_line.borrow_mut();
+        let remainder = command_line[self.prefix_len..].to_string();
+        *command_line = format!("{candidate}{remainder}");
+    }
+}
+
+impl Component for CompletionPopup {
+    fn render(&self, area: Rect, canvas: &mut Canvas<Window>, font: &Font) -> Result<(), String> {
+        let row_height = font.height() + 4;
+        let popup_height = row_height * self.candidates.len() as i32 + 4;
+        // Anchor just above the prompt's one-line strip at the bottom of the screen,
+        // not `area.y()` (which is the top of the whole canvas and would put the popup
+        // entirely off screen).
+        let prompt_y = area.y() + area.height() as i32 - font.height();
+        let popup = Rect::new(area.x(), (prompt_y - popup_height).max(0), 220, popup_height);
+
+        canvas.set_draw_color(Color::RGBA(30, 30, 30, 235));
+        canvas.fill_rect(popup)?;
+
+        for (i, candidate) in self.candidates.iter().enumerate() {
+            let color = if i == self.selected {
+                Color::RGBA(255, 255, 255, 255)
+            } else {
+                Color::RGBA(170, 170, 170, 255)
+            };
+            let surface = font.render(candidate).blended(color).map_err(|e| e.to_string())?;
+            let texture_creator = canvas.texture_creator();
+            let texture = texture_creator
+                .create_texture_from_surface(&surface)
+                .map_err(|e| e.to_string())?;
+            let TextureQuery { width, height, .. } = texture.query();
+            let target = Rect::new(popup.x() + 2, popup.y() + 2 + i as i32 * row_height, width, height);
+            canvas.copy(&texture, None, Some(target))?;
+        }
+
+        Ok(())
+    }
+
+    fn handle_event(&mut self, event: &Event) -> EventResult {
+        match event {
+            Event::KeyDown {
+                keycode: Some(Keycode::Tab),
+                ..
+            } => {
+                self.selected = (self.selected + 1) % self.candidates.len();
+                EventResult::Consumed(None)
+            }
+            Event::KeyDown {
+                keycode: Some(Keycode::Return),
+                ..
+            } => {
+                self.commit();
+                EventResult::Consumed(Some(AppAction::PopLayer))
+            }
+            Event::KeyDown {
+                keycode: Some(Keycode::Escape),
+                ..
+            } => EventResult::Consumed(Some(AppAction::PopLayer)),
+            // `Quit` has to keep propagating or closing the window while a completion
+            // popup is open would do nothing. Everything else is swallowed: while this
+            // popup has focus, `Prompt` underneath must not keep editing the shared
+            // `command_line` out from under `prefix_len`/`commit()`.
+            Event::Quit { .. } => EventResult::Ignored,
+            _ => EventResult::Consumed(None),
+        }
+    }
+}
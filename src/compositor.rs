@@ -0,0 +1,77 @@
+use sdl2::event::Event;
+use sdl2::rect::Rect;
+use sdl2::render::Canvas;
+use sdl2::ttf::Font;
+use sdl2::video::Window;
+
+use crate::AppAction;
+
+/// Whether a [`Component`] consumed an event. Consuming stops the event from reaching
+/// the layers underneath, optionally bubbling an [`AppAction`] up to the app.
+pub enum EventResult {
+    Consumed(Option<AppAction>),
+    Ignored,
+}
+
+/// A single layer in the [`Compositor`]'s stack: something that draws itself into a
+/// `Rect` and may react to input before it reaches the layers underneath.
+pub trait Component {
+    fn render(&self, area: Rect, canvas: &mut Canvas<Window>, font: &Font) -> Result<(), String>;
+
+    fn handle_event(&mut self, _event: &Event) -> EventResult {
+        EventResult::Ignored
+    }
+
+    /// Carves this layer's own rect out of the full area the `Compositor` was given.
+    /// Defaults to the identity (the whole area), which is right for overlays that
+    /// float over everything else; layers that dock to an edge (the prompt pinned to
+    /// the bottom, the buffer taking whatever's left above it) override this instead
+    /// of fighting over one shared full-screen rect in `render`.
+    fn layout(&self, area: Rect, _font: &Font) -> Rect {
+        area
+    }
+}
+
+/// Owns the stack of UI layers and draws/dispatches events through them back-to-front
+/// and top-down respectively, the same split Helix's compositor uses: the last pushed
+/// layer is drawn on top and gets first refusal on every event, so a focused overlay
+/// (a popup, say) can swallow input before the base layers ever see it.
+#[derive(Default)]
+pub struct Compositor {
+    layers: Vec<Box<dyn Component>>,
+}
+
+impl Compositor {
+    pub fn new() -> Self {
+        Self { layers: Vec::new() }
+    }
+
+    pub fn push(&mut self, layer: Box<dyn Component>) {
+        self.layers.push(layer);
+    }
+
+    pub fn pop(&mut self) -> Option<Box<dyn Component>> {
+        self.layers.pop()
+    }
+
+    pub fn render(&self, area: Rect, canvas: &mut Canvas<Window>, font: &Font) -> Result<(), String> {
+        for layer in &self.layers {
+            layer.render(layer.layout(area, font), canvas, font)?;
+        }
+        Ok(())
+    }
+
+    pub fn handle_event(&mut self, event: &Event) -> Vec<AppAction> {
+        let mut actions = Vec::new();
+        for layer in self.layers.iter_mut().rev() {
+            match layer.handle_event(event) {
+                EventResult::Consumed(action) => {
+                    actions.extend(action);
+                    break;
+                }
+                EventResult::Ignored => (),
+            }
+        }
+        actions
+    }
+}
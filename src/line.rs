@@ -0,0 +1,33 @@
+/// How a scrollback line should be drawn. Currently just distinguishes stderr from
+/// stdout, but gives `BufferView` a real field to key its cache and style on instead of
+/// a marker character smuggled into the text.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Style {
+    Normal,
+    Stderr,
+}
+
+impl Style {
+    pub fn color(self) -> (u8, u8, u8, u8) {
+        match self {
+            Self::Normal => (170, 170, 170, 255),
+            Self::Stderr => (220, 90, 90, 255),
+        }
+    }
+}
+
+/// A single line of scrollback output.
+#[derive(Debug, Clone)]
+pub struct Line {
+    pub text: String,
+    pub style: Style,
+}
+
+impl Line {
+    pub fn new(text: impl Into<String>, style: Style) -> Self {
+        Self {
+            text: text.into(),
+            style,
+        }
+    }
+}
@@ -7,21 +7,55 @@
     clippy::missing_panics_doc
 )]
 
+mod components;
+mod compositor;
+mod line;
+mod process;
+mod scrollback;
+mod shaping;
+
 use sdl2::event::Event;
 use sdl2::keyboard::{Keycode, Mod};
 use sdl2::pixels::Color;
 use sdl2::rect::Rect;
-use sdl2::render::{Canvas, TextureCreator, TextureQuery};
-use sdl2::video::{Window, WindowContext};
+use sdl2::render::Canvas;
+use sdl2::ttf::Hinting;
+use sdl2::video::Window;
 use sdl2::Sdl;
 
+use std::cell::RefCell;
 use std::env;
-use std::error::Error;
 use std::path::{Path, PathBuf};
-use std::process::Command;
+use std::rc::Rc;
+
+use components::{BufferView, CompletionPopup, Prompt};
+use compositor::Compositor;
+use scrollback::Scrollback;
 
 static SCREEN_WIDTH: u16 = 800;
 static SCREEN_HEIGHT: u16 = 600;
+static FONT_PATH: &str = "./assets/fonts/VictorMono-Regular.ttf";
+
+/// Rendering knobs that used to be implicit in the code. `shaping` toggles the
+/// `rustybuzz` pipeline (off by default: it's slower and most fonts don't need it);
+/// `hinting` is passed straight to `Font::set_hinting`.
+pub struct Config {
+    pub shaping: bool,
+    pub hinting: Hinting,
+    /// Caps the scrollback at this many lines, dropping the oldest once it's exceeded.
+    /// `None` leaves it unbounded.
+    pub max_scrollback_lines: Option<usize>,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            shaping: false,
+            hinting: Hinting::Normal,
+            max_scrollback_lines: Some(10_000),
+        }
+    }
+}
 
 // handle the annoying Rect i32
 macro_rules! rect(
@@ -55,15 +89,21 @@ fn get_centered_rect(rect_width: u32, rect_height: u32, cons_width: u32, cons_he
 
 struct App {
     sdl_context: Sdl,
+    // `compositor` holds BufferView's cached `Texture<'static>`s, rasterized through a
+    // `Box::leak`'d `TextureCreator` so the cache can outlive a single `render()` call.
+    // Fields drop in declaration order, so `compositor` must be listed (and therefore
+    // dropped) before `canvas`: otherwise the renderer `canvas` owns would be destroyed
+    // first, and every cached `Texture::drop` after it would call `SDL_DestroyTexture`
+    // against an already-destroyed renderer.
+    compositor: Compositor,
     canvas: Canvas<Window>,
-    texture_creator: TextureCreator<WindowContext>,
-
-    buffer: String,
-    command_line: String,
+    config: Config,
 }
 
 impl App {
     pub fn new() -> Result<Self, String> {
+        let config = Config::default();
+
         let sdl_context = sdl2::init()?;
         let video_subsys = sdl_context.video()?;
         let window = video_subsys
@@ -75,195 +115,97 @@ impl App {
             .build()
             .map_err(|e| e.to_string())?;
 
+        // Enable SDL's text input so `Event::TextInput` carries composed UTF-8 text
+        // (IME, dead keys, shift/altgr layers) instead of us reinventing it from keycodes.
+        video_subsys.text_input().start();
+
         let canvas = window.into_canvas().build().map_err(|e| e.to_string())?;
-        let texture_creator = canvas.texture_creator();
+        // Leaked once for the process lifetime so cached line textures can borrow it
+        // across frames instead of being tied to a `TextureCreator` that's recreated
+        // (and dropped) every render call.
+        let texture_creator: &'static _ = Box::leak(Box::new(canvas.texture_creator()));
+
+        // Only parse the font file a second time (for rustybuzz) when shaping is
+        // actually turned on; the common case sticks to SDL_ttf's own layout.
+        let shaping_face = if config.shaping {
+            let bytes: &'static [u8] =
+                Box::leak(std::fs::read(FONT_PATH).map_err(|e| e.to_string())?.into_boxed_slice());
+            rustybuzz::Face::from_slice(bytes, 0).map(Rc::new)
+        } else {
+            None
+        };
+
+        let buffer = Rc::new(RefCell::new(Scrollback::new(config.max_scrollback_lines)));
+        let mut compositor = Compositor::new();
+        compositor.push(Box::new(BufferView::new(
+            Rc::clone(&buffer),
+            texture_creator,
+            shaping_face.clone(),
+        )));
+        compositor.push(Box::new(Prompt::new(buffer, video_subsys, shaping_face)));
+
         Ok(Self {
             sdl_context,
+            compositor,
             canvas,
-            texture_creator,
-
-            buffer: String::new(),
-            command_line: String::new(),
+            config,
         })
     }
 
-    pub fn run_command(command_line: &str) -> Result<String, Box<dyn Error>> {
-        if command_line.is_empty() {
-            return Err("Running empty command".into());
-        }
-        let mut result = String::new();
-
-        // assume one command in command_line
-        let words: Vec<&str> = command_line.split_ascii_whitespace().collect();
-        let command = words[0];
-        let arguments = &words[0..];
-        if let Some(command) = find_command(command) {
-            result = Command::new(command)
-                .args(arguments)
-                .output()
-                .map(|out| String::from_utf8(out.stdout))??;
-        }
-
-        Ok(result)
-    }
-
     pub fn run(&mut self) -> Result<(), String> {
         let ttf_context = sdl2::ttf::init().map_err(|e| e.to_string())?;
 
         // Load a font
         //let font_path: &Path = Path::new("/usr/share/fonts/noto-cjk/NotoSansCJK-Regular.ttc");
-        let font_path: &Path = Path::new("./assets/fonts/VictorMono-Regular.ttf");
+        let font_path: &Path = Path::new(FONT_PATH);
         let mut font = ttf_context.load_font(font_path, 24)?;
         font.set_style(sdl2::ttf::FontStyle::NORMAL);
+        font.set_hinting(self.config.hinting);
 
         'mainloop: loop {
-            match self.input() {
-                Err(error) => {
-                    println!("{error}");
-                    break;
-                }
-                Ok(events) => {
-                    for event in events {
-                        match event {
-                            AppAction::None => (),
-                            AppAction::Exit => break 'mainloop,
-                            AppAction::RunCommand => {
-                                if let Ok(append) = Self::run_command(&self.command_line) {
-                                    self.buffer.push_str(&append);
-                                    self.command_line = String::new();
-                                }
-                            }
+            for event in self.sdl_context.event_pump()?.poll_iter() {
+                for action in self.compositor.handle_event(&event) {
+                    match action {
+                        AppAction::Exit => break 'mainloop,
+                        AppAction::ShowCompletions {
+                            command_line,
+                            prefix_len,
+                            candidates,
+                        } => {
+                            self.compositor.push(Box::new(CompletionPopup::new(
+                                command_line,
+                                prefix_len,
+                                candidates,
+                            )));
+                        }
+                        AppAction::PopLayer => {
+                            self.compositor.pop();
                         }
                     }
                 }
             }
 
-            let prompt = "> ";
-            // render a surface, and convert it to a texture bound to the canvas
-            let surface = font
-                .render(&format!("{}\n{prompt}{}", &self.buffer, &self.command_line))
-                .blended_wrapped(Color::RGBA(170, 170, 170, 255), 0)
-                .map_err(|e| e.to_string())?;
-            let texture = self
-                .texture_creator
-                .create_texture_from_surface(&surface)
-                .map_err(|e| e.to_string())?;
-
             self.canvas.set_draw_color(Color::RGBA(50, 50, 50, 255));
             self.canvas.clear();
 
-            let TextureQuery { width, height, .. } = texture.query();
-
-            // If the example text is too big for the screen, downscale it (and center irregardless)
-            // let padding = 64;
-            // let target = get_centered_rect(
-            //     width,
-            //     height,
-            //     SCREEN_WIDTH - padding,
-            //     SCREEN_HEIGHT - padding,
-            // );
-
-            let target = rect!(0, 0, width, height);
+            let area = rect!(0, 0, SCREEN_WIDTH, SCREEN_HEIGHT);
+            self.compositor.render(area, &mut self.canvas, &font)?;
 
-            self.canvas.copy(&texture, None, Some(target))?;
             self.canvas.present();
         }
 
         Ok(())
     }
-
-    fn input(&mut self) -> Result<Vec<AppAction>, String> {
-        let mut events = Vec::new();
-        for event in self.sdl_context.event_pump()?.poll_iter() {
-            match event {
-                Event::TextInput { .. } | Event::TextEditing { .. } => (),
-                Event::KeyDown {
-                    keycode: Some(Keycode::Escape),
-                    ..
-                }
-                | Event::Quit { .. } => events.push(AppAction::Exit),
-                Event::KeyDown {
-                    keycode: Some(Keycode::Return),
-                    ..
-                } => events.push(AppAction::RunCommand),
-                Event::KeyDown {
-                    keycode: Some(Keycode::Backspace),
-                    keymod: Mod::LSHIFTMOD,
-                    ..
-                } => {
-                    let mut words: Vec<&str> = self.command_line.split_ascii_whitespace().collect();
-                    words.pop();
-                    self.command_line = words.join(" ");
-                }
-                Event::KeyDown {
-                    keycode: Some(key),
-                    keymod: Mod::NOMOD,
-                    ..
-                } => match key {
-                    Keycode::Backspace => {
-                        self.command_line.pop();
-                    }
-                    Keycode::A => self.command_line.push('a'),
-                    Keycode::B => self.command_line.push('b'),
-                    Keycode::C => self.command_line.push('c'),
-                    Keycode::D => self.command_line.push('d'),
-                    Keycode::E => self.command_line.push('e'),
-                    Keycode::F => self.command_line.push('f'),
-                    Keycode::G => self.command_line.push('g'),
-                    Keycode::H => self.command_line.push('h'),
-                    Keycode::I => self.command_line.push('i'),
-                    Keycode::J => self.command_line.push('j'),
-                    Keycode::K => self.command_line.push('k'),
-                    Keycode::L => self.command_line.push('l'),
-                    Keycode::M => self.command_line.push('m'),
-                    Keycode::N => self.command_line.push('n'),
-                    Keycode::O => self.command_line.push('o'),
-                    Keycode::P => self.command_line.push('p'),
-                    Keycode::Q => self.command_line.push('q'),
-                    Keycode::R => self.command_line.push('r'),
-                    Keycode::S => self.command_line.push('s'),
-                    Keycode::T => self.command_line.push('t'),
-                    Keycode::U => self.command_line.push('u'),
-                    Keycode::V => self.command_line.push('v'),
-                    Keycode::W => self.command_line.push('w'),
-                    Keycode::X => self.command_line.push('x'),
-                    Keycode::Y => self.command_line.push('y'),
-                    Keycode::Z => self.command_line.push('z'),
-                    Keycode::Space => self.command_line.push(' '),
-                    Keycode::Slash => self.command_line.push('/'),
-                    Keycode::Period => self.command_line.push('.'),
-                    key => println!("Unhandled NOMOD {:?}", key),
-                },
-
-                Event::KeyDown {
-                    keycode: Some(key), ..
-                } => match key {
-                    Keycode::Space => self.command_line.push(' '),
-                    Keycode::Slash => self.command_line.push('/'),
-                    Keycode::Period => self.command_line.push('.'),
-                    key => println!("Unhandled {:?}", key),
-                },
-                other => {
-                    println!("{:?}", other);
-                }
-            }
-        }
-        if events.is_empty() {
-            events.push(AppAction::None);
-        }
-        Ok(events)
-    }
-}
-
-impl Drop for App {
-    fn drop(&mut self) {}
 }
 
-#[derive(Debug, Clone, Copy)]
 enum AppAction {
-    None,
     Exit,
-    RunCommand,
+    ShowCompletions {
+        command_line: Rc<RefCell<String>>,
+        prefix_len: usize,
+        candidates: Vec<String>,
+    },
+    PopLayer,
 }
 
 fn main() -> Result<(), String> {
@@ -277,6 +219,29 @@ fn main() -> Result<(), String> {
     Ok(())
 }
 
+fn is_ctrl_chord(event: &Event, key: Keycode) -> bool {
+    matches!(
+        event,
+        Event::KeyDown {
+            keycode: Some(k),
+            keymod,
+            ..
+        } if *k == key && keymod.intersects(Mod::LCTRLMOD | Mod::RCTRLMOD)
+    )
+}
+
+fn is_copy_event(event: &Event) -> bool {
+    is_ctrl_chord(event, Keycode::C)
+}
+
+fn is_paste_event(event: &Event) -> bool {
+    is_ctrl_chord(event, Keycode::V)
+}
+
+fn is_cut_event(event: &Event) -> bool {
+    is_ctrl_chord(event, Keycode::X)
+}
+
 pub fn find_command(to_check: &str) -> Option<PathBuf> {
     let path = env::var("PATH").unwrap_or_else(|_| String::new());
     let paths = env::split_paths(&path);
@@ -293,6 +258,29 @@ pub fn find_command(to_check: &str) -> Option<PathBuf> {
     None
 }
 
+/// Collects the names of every `PATH` entry starting with `prefix`, for tab completion.
+/// Unlike `find_command`/`find_commands`, which look up specific known names, this scans
+/// directory listings since the candidate set isn't known ahead of time.
+pub fn find_command_candidates(prefix: &str) -> Vec<String> {
+    let mut candidates = Vec::new();
+    let path = env::var("PATH").unwrap_or_else(|_| String::new());
+    for dir in env::split_paths(&path) {
+        let Ok(entries) = std::fs::read_dir(&dir) else {
+            continue;
+        };
+        for entry in entries.flatten() {
+            let Some(name) = entry.file_name().to_str().map(str::to_string) else {
+                continue;
+            };
+            if name.starts_with(prefix) && !candidates.contains(&name) {
+                candidates.push(name);
+            }
+        }
+    }
+    candidates.sort_unstable();
+    candidates
+}
+
 pub fn find_commands(to_check: &[&str]) -> Vec<Option<PathBuf>> {
     let mut found: Vec<Option<PathBuf>> = vec![None; to_check.len()];
     let path = env::var("PATH").unwrap_or_else(|_| String::new());
@@ -0,0 +1,78 @@
+use std::io::{BufRead, BufReader};
+use std::process::{Command, Stdio};
+use std::sync::mpsc::{self, Receiver};
+use std::thread;
+
+use crate::find_command;
+
+/// Which stream an [`OutputLine`] came from, so callers can style stderr differently.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Stream {
+    Stdout,
+    Stderr,
+}
+
+#[derive(Debug)]
+pub struct OutputLine {
+    pub stream: Stream,
+    pub text: String,
+}
+
+/// Spawns `command_line` and streams its stdout/stderr back over a channel instead of
+/// blocking on `Command::output()` until the child exits, the draining model Helix uses
+/// with its async backend: the render loop stays responsive and calls `try_recv` each
+/// frame for whatever arrived since the last one.
+pub fn spawn(command_line: &str) -> Result<Receiver<OutputLine>, String> {
+    let words: Vec<&str> = command_line.split_ascii_whitespace().collect();
+    let command = *words.first().ok_or("Running empty command")?;
+    let arguments = &words[1..];
+
+    let program = find_command(command).ok_or_else(|| format!("{command}: command not found"))?;
+
+    let mut child = Command::new(program)
+        .args(arguments)
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .map_err(|e| e.to_string())?;
+
+    let (tx, rx) = mpsc::channel();
+
+    let stdout = child.stdout.take().expect("child spawned with piped stdout");
+    let stdout_tx = tx.clone();
+    thread::spawn(move || {
+        for line in BufReader::new(stdout).lines().map_while(Result::ok) {
+            if stdout_tx
+                .send(OutputLine {
+                    stream: Stream::Stdout,
+                    text: line,
+                })
+                .is_err()
+            {
+                break;
+            }
+        }
+    });
+
+    let stderr = child.stderr.take().expect("child spawned with piped stderr");
+    thread::spawn(move || {
+        for line in BufReader::new(stderr).lines().map_while(Result::ok) {
+            if tx
+                .send(OutputLine {
+                    stream: Stream::Stderr,
+                    text: line,
+                })
+                .is_err()
+            {
+                break;
+            }
+        }
+    });
+
+    // Reap the child on its own thread; we don't need its exit status.
+    thread::spawn(move || {
+        let _ = child.wait();
+    });
+
+    Ok(rx)
+}
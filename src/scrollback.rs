@@ -0,0 +1,35 @@
+use crate::line::{Line, Style};
+
+/// Past command output, oldest first. Shared between [`crate::components::Prompt`],
+/// which appends to it, and [`crate::components::BufferView`], which renders a window
+/// into it.
+///
+/// Capped at an optional `max_lines` so a long-running session doesn't grow the buffer
+/// forever: once the cap is hit, the oldest lines are dropped to make room.
+pub struct Scrollback {
+    lines: Vec<Line>,
+    max_lines: Option<usize>,
+}
+
+impl Scrollback {
+    pub fn new(max_lines: Option<usize>) -> Self {
+        Self {
+            lines: Vec::new(),
+            max_lines,
+        }
+    }
+
+    pub fn push(&mut self, text: &str, style: Style) {
+        self.lines.push(Line::new(text, style));
+        if let Some(max) = self.max_lines {
+            if self.lines.len() > max {
+                let excess = self.lines.len() - max;
+                self.lines.drain(0..excess);
+            }
+        }
+    }
+
+    pub fn lines(&self) -> &[Line] {
+        &self.lines
+    }
+}
@@ -0,0 +1,88 @@
+use sdl2::pixels::Color;
+use sdl2::rect::Rect;
+use sdl2::render::{Canvas, TextureQuery};
+use sdl2::ttf::Font;
+use sdl2::video::Window;
+
+use rustybuzz::{Face, UnicodeBuffer};
+
+/// Runs `text` through `rustybuzz` and blits the shaped clusters left to right starting
+/// at `(x, y)`, returning the line height. Unlike `font.render(text)`, which lays out one
+/// codepoint per glyph, this follows the advances `rustybuzz` computed for `face` so
+/// ligatures (`->`, `=>`, ...) get the positions the font actually designed them for.
+/// SDL_ttf still does the rasterizing, one shaped cluster at a time. Clusters are mapped
+/// back to text assuming left-to-right order; right-to-left runs are not reordered and
+/// their clusters are not matched to the right text.
+pub fn shape_and_blit(
+    canvas: &mut Canvas<Window>,
+    font: &Font,
+    face: &Face,
+    text: &str,
+    color: Color,
+    x: i32,
+    y: i32,
+) -> Result<i32, String> {
+    if text.is_empty() {
+        let TextureQuery { height, .. } = font
+            .render(" ")
+            .blended(color)
+            .map_err(|e| e.to_string())
+            .and_then(|surface| {
+                canvas
+                    .texture_creator()
+                    .create_texture_from_surface(&surface)
+                    .map_err(|e| e.to_string())
+            })?
+            .query();
+        return Ok(height as i32);
+    }
+
+    let mut buffer = UnicodeBuffer::new();
+    buffer.push_str(text);
+    let glyphs = rustybuzz::shape(face, &[], buffer);
+
+    let infos = glyphs.glyph_infos();
+    let positions = glyphs.glyph_positions();
+
+    let mut pen_x = x;
+    let mut line_height = 0;
+    let units_per_em = f32::from(face.units_per_em());
+    let scale = font.height() as f32 / units_per_em;
+
+    for (i, position) in positions.iter().enumerate() {
+        let info = &infos[i];
+        // rustybuzz clusters map back to byte offsets in the original run; render the
+        // whole cluster (which may be several codepoints fused into one ligature glyph)
+        // as a single image rather than one glyph id at a time, since SDL_ttf only knows
+        // how to rasterize by codepoint, not by `GlyphId`. This assumes left-to-right
+        // clusters; no bidi reordering is done.
+        let start = info.cluster as usize;
+        let end = infos
+            .get(i + 1)
+            .map_or(text.len(), |next| next.cluster as usize);
+        let cluster_text = &text[start.min(end)..start.max(end)];
+        if cluster_text.is_empty() {
+            continue;
+        }
+
+        let surface = font
+            .render(cluster_text)
+            .blended(color)
+            .map_err(|e| e.to_string())?;
+        let texture = canvas
+            .texture_creator()
+            .create_texture_from_surface(&surface)
+            .map_err(|e| e.to_string())?;
+
+        let x_offset = (position.x_offset as f32 * scale) as i32;
+        let y_offset = (position.y_offset as f32 * scale) as i32;
+        let TextureQuery { width, height, .. } = texture.query();
+        let target = Rect::new(pen_x + x_offset, y + y_offset, width, height);
+        canvas.copy(&texture, None, Some(target))?;
+
+        pen_x += (position.x_advance as f32 * scale) as i32;
+        line_height = line_height.max(height as i32);
+    }
+
+    Ok(line_height)
+}